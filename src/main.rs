@@ -1,9 +1,13 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use std::env;
 
 use bevy::{
 	core::FixedTimestep,
+	input::mouse::MouseMotion,
+	math::{DQuat, DVec3},
 	prelude::*,
+	render::mesh::PrimitiveTopology,
 	diagnostic::{
 		FrameTimeDiagnosticsPlugin,
 		LogDiagnosticsPlugin,
@@ -21,20 +25,140 @@ use bevy_mod_raycast::{
 #[derive(Default)]
 struct GameSettings {
 	planets: i32,
+	stars_max_magnitude: f32,
+	star_seed: Option<u64>,
+	asteroid_belt_inner: f32,
+	asteroid_belt_outer: f32,
+	asteroid_belt_density: f32,
 }
 
 #[derive(Component)]
 struct Planetoid {
-	speed: f64,
-	orbit_radius: f32,
-	time: f64,
+	semi_major_axis: f64,
+	eccentricity: f64,
+	inclination: f64,
+	longitude_of_ascending_node: f64,
+	argument_of_periapsis: f64,
+	mean_anomaly_at_epoch: f64,
 }
 
 #[derive(Component)]
 struct Pickable;
 
+// Authoritative f64 world position; recenter_world projects it into Transform.
+#[derive(Component, Default)]
+struct WorldCoord(DVec3);
+
+#[derive(Component)]
+struct MainCamera;
+
+#[derive(Component)]
+struct FlyCam;
+
+struct MovementSettings {
+	sensitivity: f32,
+	speed: f32,
+}
+
+impl Default for MovementSettings {
+	fn default() -> Self {
+		Self {
+			sensitivity: 0.00012,
+			speed: 0.5,
+		}
+	}
+}
+
+#[derive(Default)]
+struct InputState {
+	pitch: f32,
+	yaw: f32,
+}
+
+#[derive(Component)]
+struct OrbitRing;
+
+// Single source of truth for both mouse-pick and map-mode keyboard selection.
+#[derive(Default)]
+struct SelectedTarget(Option<Entity>);
+
+struct MapState {
+	active: bool,
+	zoom: f32,
+	// WorldCoord the camera had just before entering map mode, to resume from.
+	previous_world_coord: Option<DVec3>,
+}
+
+impl Default for MapState {
+	fn default() -> Self {
+		Self {
+			active: false,
+			zoom: 1.0,
+			previous_world_coord: None,
+		}
+	}
+}
+
+#[derive(Component)]
+struct Asteroid {
+	cell: (i32, i32),
+}
+
+#[derive(Default)]
+struct AsteroidBeltState {
+	spawned_cells: std::collections::HashSet<(i32, i32)>,
+}
+
+#[derive(Component)]
+struct BaseColor(Color);
+
 const TIME_STEP: f32 = 1.0 / 60.0;
 
+// Standard gravitational parameter used for mean-motion, tuned for the
+// scale of this demo's orbits rather than any real celestial body.
+const GM: f64 = 0.05;
+
+// Stars dimmer than this apparent magnitude are skipped entirely.
+const STARS_MAX_MAGNITUDE: f32 = 5.5;
+// How many candidate stars to roll before filtering by magnitude.
+const STAR_CANDIDATE_COUNT: u32 = 2000;
+// Distance from the camera at which star spheres are placed; since
+// they're parented to the camera this only needs to clear the near scene.
+const STAR_SKY_RADIUS: f32 = 50.0;
+// Brightness palette size stars share materials from, instead of one per star.
+const STAR_BRIGHTNESS_BUCKETS: u32 = 16;
+
+const ASTEROID_BELT_INNER: f32 = 0.9;
+const ASTEROID_BELT_OUTER: f32 = 1.3;
+const ASTEROID_BELT_DENSITY: f32 = 0.3;
+const ASTEROID_SPAWN_STEP: f32 = 0.05;
+const ASTEROID_VIEW_RADIUS: f32 = 0.5;
+const ASTEROID_UPDATE_INTERVAL: f64 = 0.5;
+
+const PROXIMITY_GLOW_RADIUS: f32 = 0.1;
+
+// Solve Kepler's equation `M = E - e*sin(E)` for the eccentric anomaly `E`
+// via Newton-Raphson, starting from a high-eccentricity-friendly guess.
+fn solve_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+	let m = mean_anomaly.rem_euclid(std::f64::consts::TAU);
+	let mut e = if eccentricity > 0.8 {
+		m + eccentricity * m.sin().signum()
+	} else {
+		m
+	};
+
+	for _ in 0..5 {
+		let delta = (e - eccentricity * e.sin() - m) / (1.0 - eccentricity * e.cos());
+		e -= delta;
+
+		if delta.abs() < 1e-8 {
+			break;
+		}
+	}
+
+	e
+}
+
 fn main() {
 	let args: Vec<String> = env::args().collect();
 	let planets: i32 = args[1].trim().parse()
@@ -46,18 +170,46 @@ fn main() {
 		.add_plugin(FrameTimeDiagnosticsPlugin::default())
 		.add_plugin(DefaultRaycastingPlugin::<Pickable>::default())
 		.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
-		.insert_resource(GameSettings { planets })
+		.insert_resource(GameSettings {
+			planets,
+			stars_max_magnitude: STARS_MAX_MAGNITUDE,
+			star_seed: None,
+			asteroid_belt_inner: ASTEROID_BELT_INNER,
+			asteroid_belt_outer: ASTEROID_BELT_OUTER,
+			asteroid_belt_density: ASTEROID_BELT_DENSITY,
+		})
+		.insert_resource(MovementSettings::default())
+		.insert_resource(InputState::default())
+		.insert_resource(SelectedTarget::default())
+		.insert_resource(MapState::default())
+		.insert_resource(AsteroidBeltState::default())
 		.add_startup_system(setup)
+		.add_startup_system(initial_grab_cursor)
 		.add_system_to_stage(
 			CoreStage::PreUpdate,
 			update_raycast_with_cursor.before(RaycastSystem::BuildRays),
 		)
+		.add_system_to_stage(CoreStage::PreUpdate, recenter_world)
 		.add_system_set(
 			SystemSet::new()
 				.with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
 				.with_system(planetoid_movement_system),
 		)
+		.add_system_set(
+			SystemSet::new()
+				.with_run_criteria(FixedTimestep::step(ASTEROID_UPDATE_INTERVAL))
+				.with_system(stream_asteroid_belt),
+		)
+		.add_system(cursor_grab)
+		.add_system(player_look)
+		.add_system(player_move)
 		.add_system(pick_planetoid)
+		.add_system(toggle_map_mode)
+		.add_system(map_zoom_system)
+		.add_system(cycle_selected_target)
+		.add_system(map_camera_system)
+		.add_system(update_orbit_ring_visibility)
+		.add_system(proximity_glow_system)
 		.add_system(bevy::input::system::exit_on_esc_system)
 		.run();
 }
@@ -99,6 +251,16 @@ fn setup(
 
 	// Planets
 	for i in 0..settings.planets {
+		let planet_planetoid = Planetoid {
+			semi_major_axis: (0.15 + ((i as f32) / 6.0)) as f64,
+			eccentricity: rng.gen_range(0.0..0.3),
+			inclination: rng.gen_range(-0.2..0.2),
+			longitude_of_ascending_node: rng.gen_range(0.0..std::f64::consts::TAU),
+			argument_of_periapsis: rng.gen_range(0.0..std::f64::consts::TAU),
+			mean_anomaly_at_epoch: rng.gen_range(0.0..std::f64::consts::TAU),
+		};
+		let planet_ring_mesh = meshes.add(build_orbit_ring_mesh(&planet_planetoid));
+
 		commands
 			.spawn_bundle(PbrBundle {
 				mesh: meshes.add(Mesh::from(shape::UVSphere {
@@ -116,6 +278,16 @@ fn setup(
 				let moons = rng.gen_range(1..4);
 				// Moons
 				for j in 0..moons {
+					let moon_planetoid = Planetoid {
+						semi_major_axis: (0.05 + ((j as f32) / 35.0)) as f64,
+						eccentricity: rng.gen_range(0.0..0.1),
+						inclination: rng.gen_range(-0.2..0.2),
+						longitude_of_ascending_node: rng.gen_range(0.0..std::f64::consts::TAU),
+						argument_of_periapsis: rng.gen_range(0.0..std::f64::consts::TAU),
+						mean_anomaly_at_epoch: rng.gen_range(0.0..std::f64::consts::TAU),
+					};
+					let moon_ring_mesh = meshes.add(build_orbit_ring_mesh(&moon_planetoid));
+
 					parent
 						.spawn_bundle(PbrBundle {
 							mesh: meshes.add(Mesh::from(shape::UVSphere {
@@ -129,18 +301,40 @@ fn setup(
 							transform: Transform::from_xyz(0.0, 0.0, 0.0),
 							..Default::default()
 						})
-						.insert(Planetoid {
-							speed: rng.gen_range(1.0..2.0),
-							orbit_radius: 0.05 + ((j as f32) / 35.0),
-							time: rng.gen_range(0.0..10.0),
-						});
+						.insert(moon_planetoid)
+						.insert(BaseColor(Color::WHITE));
+
+					parent
+						.spawn_bundle(PbrBundle {
+							mesh: moon_ring_mesh,
+							material: materials.add(StandardMaterial {
+								base_color: Color::rgba(0.4, 0.4, 0.4, 0.6),
+								unlit: true,
+								..Default::default()
+							}),
+							visibility: Visibility { is_visible: false },
+							..Default::default()
+						})
+						.insert(OrbitRing);
 				}
 			})
-			.insert(Planetoid {
-				speed: rng.gen_range(0.1..0.5),
-				orbit_radius: 0.15 + ((i as f32) / 6.0),
-				time: rng.gen_range(0.0..10.0),
-			});
+			.insert(planet_planetoid)
+			.insert(BaseColor(Color::LIME_GREEN))
+			.insert(WorldCoord::default());
+
+		commands
+			.spawn_bundle(PbrBundle {
+				mesh: planet_ring_mesh,
+				material: materials.add(StandardMaterial {
+					base_color: Color::rgba(0.4, 0.4, 0.4, 0.6),
+					unlit: true,
+					..Default::default()
+				}),
+				visibility: Visibility { is_visible: false },
+				..Default::default()
+			})
+			.insert(OrbitRing)
+			.insert(WorldCoord::default());
 	}
 
 	// Sun
@@ -171,19 +365,96 @@ fn setup(
 		});
 
 	// camera
+	//
+	// Its rendered Transform.translation is always kept at Vec3::ZERO: the
+	// camera's true position lives in WorldCoord, and recenter_world renders
+	// every other body relative to that. The rotation from the spawn-time
+	// `looking_at` below is replaced by player_look's yaw/pitch (starting at
+	// zero) on the first frame the cursor is locked.
 	commands
 		.spawn_bundle(PerspectiveCameraBundle {
-			transform: Transform::from_xyz(0.0, 2.0, 0.0).looking_at(Vec3::ZERO, Vec3::X),
+			transform: Transform::from_xyz(0.0, 2.0, 0.0)
+				.looking_at(Vec3::ZERO, Vec3::X)
+				.with_translation(Vec3::ZERO),
 			..Default::default()
 		})
-		.insert(RayCastSource::<Pickable>::new());
+		.insert(RayCastSource::<Pickable>::new())
+		.insert(MainCamera)
+		.insert(FlyCam)
+		.insert(WorldCoord(DVec3::new(0.0, 2.0, 0.0)))
+		.with_children(|parent| {
+			spawn_starfield(parent, &mut meshes, &mut materials, &settings);
+		});
+}
+
+// Procedural star field: small unlit spheres parented to the camera so they stay at
+// infinity regardless of how far the camera travels. Candidate stars are
+// rolled with random directions and magnitudes, then only those brighter
+// than `GameSettings::stars_max_magnitude` are kept.
+fn spawn_starfield(
+	parent: &mut ChildBuilder,
+	meshes: &mut Assets<Mesh>,
+	materials: &mut Assets<StandardMaterial>,
+	settings: &GameSettings,
+) {
+	let mut rng = match settings.star_seed {
+		Some(seed) => StdRng::seed_from_u64(seed),
+		None => StdRng::from_entropy(),
+	};
+
+	let star_mesh = meshes.add(Mesh::from(shape::UVSphere {
+		radius: 0.02,
+		..Default::default()
+	}));
+
+	// Stars only differ by brightness, so quantize into a small palette and
+	// share material handles instead of allocating one per star.
+	let mut brightness_materials: std::collections::HashMap<u32, Handle<StandardMaterial>> =
+		std::collections::HashMap::new();
+
+	for _ in 0..STAR_CANDIDATE_COUNT {
+		let magnitude = rng.gen_range(-1.5..8.0);
+
+		if magnitude > settings.stars_max_magnitude {
+			continue;
+		}
+
+		let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+		let phi = rng.gen_range(-1.0f32..1.0).acos();
+		let direction = Vec3::new(
+			phi.sin() * theta.cos(),
+			phi.cos(),
+			phi.sin() * theta.sin(),
+		);
+
+		let intensity = (10f32.powf(-0.4 * magnitude)).min(1.0);
+		let bucket = (intensity * STAR_BRIGHTNESS_BUCKETS as f32) as u32;
+		let material = brightness_materials.entry(bucket).or_insert_with(|| {
+			let quantized = bucket as f32 / STAR_BRIGHTNESS_BUCKETS as f32;
+			let color = Color::rgb(quantized, quantized, quantized);
+			materials.add(StandardMaterial {
+				base_color: color,
+				emissive: color,
+				unlit: true,
+				..Default::default()
+			})
+		});
+
+		parent
+			.spawn_bundle(PbrBundle {
+				mesh: star_mesh.clone(),
+				material: material.clone(),
+				transform: Transform::from_translation(direction * STAR_SKY_RADIUS),
+				..Default::default()
+			});
+	}
 }
 
 fn pick_planetoid(
-	planetoids_query: Query<(&Planetoid, &GlobalTransform, &Handle<StandardMaterial>)>,
-	mut materials: ResMut<Assets<StandardMaterial>>,
+	planetoids_query: Query<(Entity, &GlobalTransform), With<Planetoid>>,
 	to: Query<&RayCastSource<Pickable>>,
 	mouse_event: Res<Input<MouseButton>>,
+	mut selected: ResMut<SelectedTarget>,
 ) {
 	if let Ok(raycast_source) = to.get_single() {
 		if let Some(top_intersection) = raycast_source.intersect_top() {
@@ -192,47 +463,473 @@ fn pick_planetoid(
 
 			if mouse_event.just_pressed(MouseButton::Left) {
 				let mut shortest_distance = 100.0;
+				let mut closest: Option<Entity> = None;
 
-				// Lets just change color of the closest planetoid for now
-				let mut closest_color_handle : Option<&Handle<StandardMaterial>> = None;
-
-				for (_planetoid, transform, handle) in planetoids_query.iter() {
+				for (entity, transform) in planetoids_query.iter() {
 					let current_distance = transform.translation.distance(new_position);
 
-					// Reset all the colors
-					let color = &mut materials.get_mut(handle).unwrap().base_color;
-					color.set_r(0.0);
-					color.set_g(1.0);
-					color.set_b(0.0);
-
 					if current_distance < shortest_distance {
 						shortest_distance = current_distance;
 
-						closest_color_handle = Some(handle);
+						closest = Some(entity);
 					}
 				}
 
 				println!("{} shortest_distance", shortest_distance);
 
-				if closest_color_handle.is_some() {
-					let color = &mut materials.get_mut(closest_color_handle.unwrap()).unwrap().base_color;
-					color.set_r(1.0);
-					color.set_g(1.0);
-					color.set_b(1.0);
+				if closest.is_some() {
+					selected.0 = closest;
 				}
 			}
 		}
 	}
 }
 
+fn proximity_glow_system(
+	selected: Res<SelectedTarget>,
+	camera_query: Query<&GlobalTransform, With<MainCamera>>,
+	planetoids_query: Query<(&GlobalTransform, &BaseColor, &Handle<StandardMaterial>), With<Planetoid>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+	let camera_position = match camera_query.get_single() {
+		Ok(transform) => transform.translation,
+		Err(_) => return,
+	};
+
+	let selected_position = selected.0.and_then(|entity| {
+		planetoids_query
+			.get(entity)
+			.ok()
+			.map(|(transform, _, _)| transform.translation)
+	});
+
+	for (transform, base_color, handle) in planetoids_query.iter() {
+		let mut distance = transform.translation.distance(camera_position);
+
+		if let Some(position) = selected_position {
+			distance = distance.min(transform.translation.distance(position));
+		}
+
+		let glow = (1.0 - distance / PROXIMITY_GLOW_RADIUS).clamp(0.0, 1.0);
+		let material = materials.get_mut(handle).unwrap();
+
+		material.base_color = base_color.0;
+		material.emissive = Color::rgba(glow, glow, glow, 1.0);
+	}
+}
+
+fn toggle_map_mode(
+	keys: Res<Input<KeyCode>>,
+	mut map_state: ResMut<MapState>,
+	mut camera_query: Query<&mut WorldCoord, With<MainCamera>>,
+) {
+	if !keys.just_pressed(KeyCode::M) {
+		return;
+	}
+
+	map_state.active = !map_state.active;
+
+	let mut camera_world_coord = match camera_query.get_single_mut() {
+		Ok(world_coord) => world_coord,
+		Err(_) => return,
+	};
+
+	if map_state.active {
+		map_state.previous_world_coord = Some(camera_world_coord.0);
+		camera_world_coord.0 = DVec3::new(0.0, 2.0 * map_state.zoom as f64, 0.0);
+	} else if let Some(previous) = map_state.previous_world_coord.take() {
+		camera_world_coord.0 = previous;
+	}
+}
+
+fn map_zoom_system(keys: Res<Input<KeyCode>>, mut map_state: ResMut<MapState>) {
+	if !map_state.active {
+		return;
+	}
+
+	if keys.pressed(KeyCode::W) || keys.pressed(KeyCode::Up) {
+		map_state.zoom = (map_state.zoom - 0.02).max(0.1);
+	}
+
+	if keys.pressed(KeyCode::S) || keys.pressed(KeyCode::Down) {
+		map_state.zoom = (map_state.zoom + 0.02).min(5.0);
+	}
+}
+
+fn cycle_selected_target(
+	keys: Res<Input<KeyCode>>,
+	map_state: Res<MapState>,
+	planetoids_query: Query<Entity, With<Planetoid>>,
+	mut selected: ResMut<SelectedTarget>,
+) {
+	if !map_state.active {
+		return;
+	}
+
+	let advance = keys.just_pressed(KeyCode::D) || keys.just_pressed(KeyCode::Right);
+	let retreat = keys.just_pressed(KeyCode::A) || keys.just_pressed(KeyCode::Left);
+
+	if !advance && !retreat {
+		return;
+	}
+
+	let entities: Vec<Entity> = planetoids_query.iter().collect();
+	if entities.is_empty() {
+		return;
+	}
+
+	let current_index = selected
+		.0
+		.and_then(|entity| entities.iter().position(|&candidate| candidate == entity));
+
+	let next_index = match current_index {
+		Some(index) if advance => (index + 1) % entities.len(),
+		Some(index) => (index + entities.len() - 1) % entities.len(),
+		None => 0,
+	};
+
+	selected.0 = Some(entities[next_index]);
+}
+
+// Only the camera's WorldCoord moves to the overhead vantage here; its
+// Transform.translation must stay at Vec3::ZERO (see setup()), so we derive
+// the look-down rotation from a throwaway Transform instead of assigning
+// the whole thing.
+fn map_camera_system(
+	map_state: Res<MapState>,
+	mut query: Query<(&mut Transform, &mut WorldCoord), With<FlyCam>>,
+) {
+	if !map_state.active {
+		return;
+	}
+
+	for (mut transform, mut world_coord) in query.iter_mut() {
+		let height = 2.0 * map_state.zoom as f64;
+		let world_position = DVec3::new(0.0, height, 0.0);
+
+		world_coord.0 = world_position;
+		transform.rotation = Transform::from_translation(world_position.as_vec3())
+			.looking_at(Vec3::ZERO, Vec3::new(0.0, 0.0, -1.0))
+			.rotation;
+	}
+}
+
+fn update_orbit_ring_visibility(
+	map_state: Res<MapState>,
+	mut rings_query: Query<&mut Visibility, With<OrbitRing>>,
+) {
+	for mut visibility in rings_query.iter_mut() {
+		visibility.is_visible = map_state.active;
+	}
+}
+
+fn cell_seed(cell: (i32, i32)) -> u64 {
+	let x = cell.0 as i64 as u64;
+	let y = cell.1 as i64 as u64;
+
+	x.wrapping_mul(0x9E3779B97F4A7C15).wrapping_add(y)
+}
+
+// Asteroids get a Planetoid + WorldCoord like any other body, so
+// planetoid_movement_system/recenter_world already know how to orbit and render them.
+fn stream_asteroid_belt(
+	time: Res<Time>,
+	settings: Res<GameSettings>,
+	mut belt_state: ResMut<AsteroidBeltState>,
+	mut commands: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+	camera_query: Query<&WorldCoord, With<MainCamera>>,
+	asteroids_query: Query<(Entity, &Asteroid)>,
+) {
+	let camera_coord = match camera_query.get_single() {
+		Ok(world_coord) => world_coord.0,
+		Err(_) => return,
+	};
+
+	let step = ASTEROID_SPAWN_STEP as f64;
+	let view_radius = ASTEROID_VIEW_RADIUS as f64;
+	let cell_span = (view_radius / step).ceil() as i32;
+
+	let camera_cell = (
+		(camera_coord.x / step).round() as i32,
+		(camera_coord.z / step).round() as i32,
+	);
+
+	let mut cells_in_view: std::collections::HashSet<(i32, i32)> = std::collections::HashSet::new();
+
+	for di in -cell_span..=cell_span {
+		for dj in -cell_span..=cell_span {
+			let cell = (camera_cell.0 + di, camera_cell.1 + dj);
+			let cell_pos = DVec3::new(cell.0 as f64 * step, 0.0, cell.1 as f64 * step);
+
+			if (cell_pos - camera_coord).length() > view_radius {
+				continue;
+			}
+
+			let radius_from_sun = (cell_pos.x * cell_pos.x + cell_pos.z * cell_pos.z).sqrt();
+			if radius_from_sun < settings.asteroid_belt_inner as f64
+				|| radius_from_sun > settings.asteroid_belt_outer as f64
+			{
+				continue;
+			}
+
+			cells_in_view.insert(cell);
+
+			if belt_state.spawned_cells.contains(&cell) {
+				continue;
+			}
+
+			belt_state.spawned_cells.insert(cell);
+
+			let mut rng = StdRng::seed_from_u64(cell_seed(cell));
+
+			if rng.gen::<f32>() > settings.asteroid_belt_density {
+				continue;
+			}
+
+			let radius = rng.gen_range(0.001..0.004);
+			let rotation = Quat::from_rotation_x(rng.gen_range(0.0..std::f32::consts::TAU))
+				* Quat::from_rotation_y(rng.gen_range(0.0..std::f32::consts::TAU))
+				* Quat::from_rotation_z(rng.gen_range(0.0..std::f32::consts::TAU));
+
+			// cell_pos.z.atan2(cell_pos.x) is the angle we want the asteroid to
+			// occupy *right now*, but orbital_offset reconstructs mean_anomaly as
+			// mean_anomaly_at_epoch + mean_motion * t using absolute elapsed time,
+			// so the epoch angle has to be wound back by however far the orbit
+			// has already progressed. The node and periapsis rotations about Y
+			// turn the in-plane angle backwards, so they are added back, and the
+			// resulting true anomaly is converted to a mean anomaly.
+			let semi_major_axis = radius_from_sun.max(0.01);
+			let mean_motion = (GM / semi_major_axis.powi(3)).sqrt();
+
+			let eccentricity: f64 = rng.gen_range(0.0..0.05);
+			let inclination: f64 = rng.gen_range(-0.05..0.05);
+			let longitude_of_ascending_node: f64 = rng.gen_range(0.0..std::f64::consts::TAU);
+			let argument_of_periapsis: f64 = rng.gen_range(0.0..std::f64::consts::TAU);
+
+			let true_anomaly =
+				cell_pos.z.atan2(cell_pos.x) + longitude_of_ascending_node + argument_of_periapsis;
+			let eccentric_anomaly = 2.0
+				* ((1.0 - eccentricity).sqrt() * (true_anomaly / 2.0).sin())
+					.atan2((1.0 + eccentricity).sqrt() * (true_anomaly / 2.0).cos());
+			let mean_anomaly_now = eccentric_anomaly - eccentricity * eccentric_anomaly.sin();
+
+			let planetoid = Planetoid {
+				semi_major_axis,
+				eccentricity,
+				inclination,
+				longitude_of_ascending_node,
+				argument_of_periapsis,
+				mean_anomaly_at_epoch: mean_anomaly_now
+					- mean_motion * time.seconds_since_startup(),
+			};
+
+			commands
+				.spawn_bundle(PbrBundle {
+					mesh: meshes.add(Mesh::from(shape::Icosphere {
+						radius,
+						subdivisions: 1,
+					})),
+					material: materials.add(StandardMaterial {
+						base_color: Color::GRAY,
+						..Default::default()
+					}),
+					transform: Transform::from_rotation(rotation),
+					..Default::default()
+				})
+				.insert(Asteroid { cell })
+				.insert(planetoid)
+				.insert(WorldCoord::default())
+				.insert(BaseColor(Color::GRAY));
+		}
+	}
+
+	for (entity, asteroid) in asteroids_query.iter() {
+		if !cells_in_view.contains(&asteroid.cell) {
+			commands.entity(entity).despawn();
+			belt_state.spawned_cells.remove(&asteroid.cell);
+		}
+	}
+}
+
+// Position in the orbital frame (offset from the parent body) at time `t`,
+// via Kepler's equation and a ZXZ-style element composition. The orbital
+// plane's normal is world Y here (orbits live in the XZ plane), so the
+// ascending node and argument of periapsis rotate about Y instead of Z.
+fn orbital_offset(planetoid: &Planetoid, t: f64) -> DVec3 {
+	let mean_motion = (GM / planetoid.semi_major_axis.powi(3)).sqrt();
+	let mean_anomaly = planetoid.mean_anomaly_at_epoch + mean_motion * t;
+	let eccentric_anomaly = solve_kepler(mean_anomaly, planetoid.eccentricity);
+
+	let e = planetoid.eccentricity;
+	let true_anomaly = 2.0
+		* ((1.0 + e).sqrt() * (eccentric_anomaly / 2.0).sin())
+			.atan2((1.0 - e).sqrt() * (eccentric_anomaly / 2.0).cos());
+	let radius = planetoid.semi_major_axis * (1.0 - e * eccentric_anomaly.cos());
+
+	let position_in_plane = DVec3::new(radius * true_anomaly.cos(), 0.0, radius * true_anomaly.sin());
+
+	let orientation = DQuat::from_rotation_y(planetoid.longitude_of_ascending_node)
+		* DQuat::from_rotation_x(planetoid.inclination)
+		* DQuat::from_rotation_y(planetoid.argument_of_periapsis);
+
+	orientation * position_in_plane
+}
+
+const ORBIT_RING_SEGMENTS: usize = 128;
+
+fn build_orbit_ring_mesh(planetoid: &Planetoid) -> Mesh {
+	let e = planetoid.eccentricity;
+	let orientation = DQuat::from_rotation_y(planetoid.longitude_of_ascending_node)
+		* DQuat::from_rotation_x(planetoid.inclination)
+		* DQuat::from_rotation_y(planetoid.argument_of_periapsis);
+
+	let positions: Vec<[f32; 3]> = (0..=ORBIT_RING_SEGMENTS)
+		.map(|step| {
+			let true_anomaly = (step as f64 / ORBIT_RING_SEGMENTS as f64) * std::f64::consts::TAU;
+			let radius =
+				planetoid.semi_major_axis * (1.0 - e * e) / (1.0 + e * true_anomaly.cos());
+			let position_in_plane =
+				DVec3::new(radius * true_anomaly.cos(), 0.0, radius * true_anomaly.sin());
+
+			(orientation * position_in_plane).as_vec3().to_array()
+		})
+		.collect();
+
+	let vertex_count = positions.len();
+
+	let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+	mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+	// StandardMaterial's pipeline specializes on normal/UV as well as
+	// position, so these are required even though the unlit ring material
+	// never samples them.
+	mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; vertex_count]);
+	mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; vertex_count]);
+	mesh
+}
+
 fn planetoid_movement_system(
 	time: Res<Time>,
-	mut planetoids_query: Query<(&Planetoid, &mut Transform)>,
+	mut planets_query: Query<(&Planetoid, &mut WorldCoord)>,
+	mut moons_query: Query<(&Planetoid, &mut Transform), Without<WorldCoord>>,
+) {
+	let t = time.seconds_since_startup();
+
+	// Planets: authoritative position lives in WorldCoord; recenter_world
+	// projects it into the camera-local Transform.
+	for (planetoid, mut world_coord) in planets_query.iter_mut() {
+		world_coord.0 = orbital_offset(planetoid, t);
+	}
+
+	// Moons: small local offsets from their parent planet, cheap enough to
+	// stay in f32 Transform space directly.
+	for (planetoid, mut transform) in moons_query.iter_mut() {
+		transform.translation = orbital_offset(planetoid, t).as_vec3();
+	}
+}
+
+fn recenter_world(
+	camera_query: Query<&WorldCoord, With<MainCamera>>,
+	mut bodies_query: Query<(&WorldCoord, &mut Transform), Without<MainCamera>>,
+) {
+	let camera_coord = match camera_query.get_single() {
+		Ok(world_coord) => world_coord.0,
+		Err(_) => return,
+	};
+
+	for (world_coord, mut transform) in bodies_query.iter_mut() {
+		transform.translation = (world_coord.0 - camera_coord).as_vec3();
+	}
+}
+
+fn initial_grab_cursor(mut windows: ResMut<Windows>) {
+	if let Some(window) = windows.get_primary_mut() {
+		window.set_cursor_lock_mode(true);
+		window.set_cursor_visibility(false);
+	}
+}
+
+fn cursor_grab(keys: Res<Input<KeyCode>>, mut windows: ResMut<Windows>) {
+	let window = match windows.get_primary_mut() {
+		Some(window) => window,
+		None => return,
+	};
+
+	if keys.just_pressed(KeyCode::Tab) {
+		let locked = !window.cursor_locked();
+		window.set_cursor_lock_mode(locked);
+		window.set_cursor_visibility(!locked);
+	}
+}
+
+fn player_look(
+	settings: Res<MovementSettings>,
+	map_state: Res<MapState>,
+	windows: Res<Windows>,
+	mut state: ResMut<InputState>,
+	mut motion: EventReader<MouseMotion>,
+	mut query: Query<&mut Transform, With<FlyCam>>,
+) {
+	if map_state.active {
+		return;
+	}
+
+	let window = match windows.get_primary() {
+		Some(window) => window,
+		None => return,
+	};
+
+	if !window.cursor_locked() {
+		return;
+	}
+
+	let window_scale = window.height().min(window.width());
+
+	for ev in motion.iter() {
+		state.yaw -= (settings.sensitivity * ev.delta.x * window_scale).to_radians();
+		state.pitch -= (settings.sensitivity * ev.delta.y * window_scale).to_radians();
+		state.pitch = state.pitch.clamp(-1.54, 1.54);
+	}
+
+	for mut transform in query.iter_mut() {
+		transform.rotation =
+			Quat::from_axis_angle(Vec3::Y, state.yaw) * Quat::from_axis_angle(Vec3::X, state.pitch);
+	}
+}
+
+// Moves the camera's WorldCoord, not its Transform, so recenter_world stays
+// correct.
+fn player_move(
+	keys: Res<Input<KeyCode>>,
+	time: Res<Time>,
+	settings: Res<MovementSettings>,
+	map_state: Res<MapState>,
+	mut query: Query<(&Transform, &mut WorldCoord), With<FlyCam>>,
 ) {
-	for (planetoid, mut transform) in planetoids_query.iter_mut() {
-		let angle = (planetoid.speed * (planetoid.time - time.seconds_since_startup())) as f32;
-		let rotation = Vec3::new(angle.cos(), 0.0, angle.sin());
+	if map_state.active {
+		return;
+	}
+
+	for (transform, mut world_coord) in query.iter_mut() {
+		let local_z = transform.local_z();
+		let forward = -Vec3::new(local_z.x, 0.0, local_z.z).normalize_or_zero();
+		let right = Vec3::new(local_z.z, 0.0, -local_z.x);
+
+		let mut velocity = Vec3::ZERO;
+		for key in keys.get_pressed() {
+			match key {
+				KeyCode::W => velocity += forward,
+				KeyCode::S => velocity -= forward,
+				KeyCode::A => velocity -= right,
+				KeyCode::D => velocity += right,
+				KeyCode::Space => velocity += Vec3::Y,
+				KeyCode::LShift => velocity -= Vec3::Y,
+				_ => (),
+			}
+		}
 
-		transform.translation = rotation * planetoid.orbit_radius;
+		let delta = velocity.normalize_or_zero() * time.delta_seconds() * settings.speed;
+		world_coord.0 += delta.as_dvec3();
 	}
 }